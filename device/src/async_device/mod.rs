@@ -1,39 +1,36 @@
-mod state_machines;
-
-use super::Event;
+use super::*;
 use core::marker::PhantomData;
 use heapless::consts::*;
 use heapless::Vec;
 use lorawan_encoding::{keys::CryptoFactory, parser::DecryptedDataPayload};
-use state_machines::Shared;
-pub use state_machines::{no_session, session, JoinAccept};
+use crate::state_machines::Shared;
+pub use crate::state_machines::{no_session, session, JoinAccept};
 
 type TimestampMs = u32;
 
-pub struct Device<'a, R, C>
+pub struct Device<R, C, B = DefaultBoardEirp>
 where
     R: radio::AsyncPhyRxTx + Timings,
     C: CryptoFactory + Default,
+    B: BoardEirp + Default,
 {
-    state: State<'a, R>,
+    shared: Shared<R>,
+    state: Option<State>,
     crypto: PhantomData<C>,
+    board: B,
 }
 
-pub enum State<'a, R>
-where
-    R: radio::AsyncPhyRxTx + Timings,
-{
-    NoSession(no_session::NoSession<'a, R>),
-    Session(session::Session<'a, R>),
+// `State` only tags which state machine is active; `shared` lives once on
+// `Device` instead of being duplicated into every variant.
+pub enum State {
+    NoSession(no_session::NoSession),
+    Session(session::Session),
 }
 
 use core::default::Default;
-impl<'a, R> State<'a, R>
-where
-    R: radio::AsyncPhyRxTx + Timings,
-{
-    fn new(shared: Shared<'a, R>) -> Self {
-        State::NoSession(no_session::NoSession::new(shared))
+impl State {
+    fn new() -> Self {
+        State::NoSession(no_session::NoSession::new())
     }
 }
 
@@ -43,10 +40,11 @@ pub trait Timings {
 }
 
 #[allow(dead_code)]
-impl<'a, R, C> Device<'a, R, C>
+impl<R, C, B> Device<R, C, B>
 where
-    R: radio::AsyncPhyRxTx + Timings + 'a,
+    R: radio::AsyncPhyRxTx + Timings,
     C: CryptoFactory + Default,
+    B: BoardEirp + Default,
 {
     pub fn new(
         region: region::Configuration,
@@ -55,56 +53,77 @@ where
         appeui: [u8; 8],
         appkey: [u8; 16],
         get_random: fn() -> u32,
-        tx_buffer: &'a mut [u8],
-    ) -> Device<'_, R, C> {
+        tx_buffer: &mut [u8],
+    ) -> Device<R, C, B> {
+        Self::new_with_board(
+            region, radio, deveui, appeui, appkey, get_random, tx_buffer, B::default(),
+        )
+    }
+
+    /// Like [`Device::new`], but with an explicit [`BoardEirp`] describing
+    /// this board's antenna gain and cable loss instead of the no-op default.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_board(
+        region: region::Configuration,
+        radio: R,
+        deveui: [u8; 8],
+        appeui: [u8; 8],
+        appkey: [u8; 16],
+        get_random: fn() -> u32,
+        tx_buffer: &mut [u8],
+        board: B,
+    ) -> Device<R, C, B> {
+        assert!(
+            tx_buffer.len() <= 256,
+            "tx_buffer of {} bytes exceeds the 256-byte TX/RX buffer capacity",
+            tx_buffer.len()
+        );
+        let buffer = Vec::from_slice(tx_buffer).unwrap();
         Device {
-            crypto: PhantomData::default(),
-            state: State::new(Shared::new(
+            shared: Shared::new(
                 radio,
-                Credentials::new(appeui, deveui, appkey),
+                Some(Credentials::new(appeui, deveui, appkey)),
                 region,
                 Mac::default(),
                 get_random,
-                tx_buffer,
-            )),
+                buffer,
+            ),
+            state: Some(State::new()),
+            crypto: PhantomData::default(),
+            board,
         }
     }
 
     pub fn get_radio(&mut self) -> &mut R {
-        let shared = self.get_shared();
-        shared.get_mut_radio()
+        self.shared.get_mut_radio()
     }
 
-    pub fn get_credentials(&mut self) -> &mut Credentials {
-        let shared = self.get_shared();
-        shared.get_mut_credentials()
-    }
-
-    fn get_shared(&mut self) -> &mut Shared<'a, R> {
-        match &mut self.state {
-            State::NoSession(state) => state.get_mut_shared(),
-            State::Session(state) => state.get_mut_shared(),
-        }
+    pub fn get_credentials(&mut self) -> &mut Option<Credentials> {
+        self.shared.get_mut_credentials()
     }
 
     pub fn get_datarate(&mut self) -> region::DR {
-        self.get_shared().get_datarate()
+        self.shared.get_datarate()
     }
 
     pub fn set_datarate(&mut self, datarate: region::DR) {
-        self.get_shared().set_datarate(datarate);
+        self.shared.set_datarate(datarate);
+    }
+
+    pub fn get_board(&self) -> &B {
+        &self.board
     }
 
     pub fn ready_to_send_data(&self) -> bool {
-        matches!(&self.state, State::Session(session::Session::Idle(_)))
+        matches!(&self.state, Some(State::Session(session::Session::Idle(_))))
     }
 
     pub async fn send(
-        self,
+        &mut self,
         data: &[u8],
         fport: u8,
         confirmed: bool,
-    ) -> (Self, Result<Response, Error<R>>) {
+    ) -> Result<Response, Error<R>> {
         self.handle_event(Event::SendDataRequest(SendData {
             data,
             fport,
@@ -114,7 +133,7 @@ where
     }
 
     pub fn get_fcnt_up(&self) -> Option<u32> {
-        if let State::Session(session) = &self.state {
+        if let Some(State::Session(session)) = &self.state {
             Some(session.get_session_data().fcnt_up())
         } else {
             None
@@ -122,7 +141,7 @@ where
     }
 
     pub fn get_session_keys(&self) -> Option<SessionKeys> {
-        if let State::Session(session) = &self.state {
+        if let Some(State::Session(session)) = &self.state {
             Some(SessionKeys::copy_from_session_data(
                 session.get_session_data(),
             ))
@@ -132,17 +151,70 @@ where
     }
 
     pub fn take_data_downlink(&mut self) -> Option<DecryptedDataPayload<Vec<u8, U256>>> {
-        self.get_shared().take_data_downlink()
+        self.shared.take_data_downlink()
     }
 
     pub fn take_join_accept(&mut self) -> Option<JoinAccept> {
-        self.get_shared().take_join_accept()
+        self.shared.take_join_accept()
     }
 
-    pub async fn handle_event(self, event: Event<R>) -> (Self, Result<Response, Error<R>>) {
-        match self.state {
-            State::NoSession(state) => state.handle_event(event).await,
-            State::Session(state) => state.handle_event(event).await,
-        }
+    pub async fn handle_event(&mut self, event: Event<'_, R>) -> Result<Response, Error<R>> {
+        let (state, result) = match self.state.take().unwrap() {
+            State::NoSession(state) => state.handle_event(event, &mut self.shared, &self.board).await,
+            State::Session(state) => state.handle_event(event, &mut self.shared, &self.board).await,
+        };
+        self.state = Some(state);
+        result
+    }
+}
+
+#[cfg(feature = "software")]
+impl<R, B> Device<R, crate::crypto::SoftwareCrypto, B>
+where
+    R: radio::AsyncPhyRxTx + Timings,
+    B: BoardEirp + Default,
+{
+    /// Like [`Device::new`], but picks the `software` feature's
+    /// [`SoftwareCrypto`](crate::crypto::SoftwareCrypto) backend so `C`
+    /// doesn't need to be named at the call site.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_default(
+        region: region::Configuration,
+        radio: R,
+        deveui: [u8; 8],
+        appeui: [u8; 8],
+        appkey: [u8; 16],
+        get_random: fn() -> u32,
+        tx_buffer: &mut [u8],
+    ) -> Self {
+        Device::new(
+            region, radio, deveui, appeui, appkey, get_random, tx_buffer,
+        )
+    }
+}
+
+#[cfg(feature = "hardware-aes")]
+impl<R, H, B> Device<R, crate::crypto::HardwareCrypto<H>, B>
+where
+    R: radio::AsyncPhyRxTx + Timings,
+    H: crate::crypto::HardwareAes128,
+    B: BoardEirp + Default,
+{
+    /// Like [`Device::new`], but picks the `hardware-aes` feature's
+    /// [`HardwareCrypto`](crate::crypto::HardwareCrypto) backend so `C`
+    /// doesn't need to be named at the call site.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_default(
+        region: region::Configuration,
+        radio: R,
+        deveui: [u8; 8],
+        appeui: [u8; 8],
+        appkey: [u8; 16],
+        get_random: fn() -> u32,
+        tx_buffer: &mut [u8],
+    ) -> Self {
+        Device::new(
+            region, radio, deveui, appeui, appkey, get_random, tx_buffer,
+        )
     }
 }
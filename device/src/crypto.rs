@@ -0,0 +1,83 @@
+//! Built-in [`CryptoFactory`](lorawan_encoding::keys::CryptoFactory) backends, enabled via Cargo features.
+//!
+//! Naming a `CryptoFactory` at every call site is awkward for the common
+//! case, and hand-rolling one is unnecessary busywork for `no_std` users who
+//! just want AES-128 to work. Enable the `software` feature for a portable
+//! pure-Rust backend, or `hardware-aes` to delegate to a board's AES
+//! peripheral through [`HardwareAes128`].
+
+#[cfg(feature = "software")]
+pub use lorawan_encoding::default_crypto::DefaultFactory as SoftwareCrypto;
+
+#[cfg(feature = "hardware-aes")]
+use core::marker::PhantomData;
+#[cfg(feature = "hardware-aes")]
+use lorawan_encoding::keys::{CryptoFactory, Decrypter, Encrypter, AES128};
+
+/// A board-provided AES-128 block cipher, e.g. a hardware crypto accelerator.
+///
+/// Implement this against whatever peripheral API the board exposes and
+/// wrap it in [`HardwareCrypto`] to get a [`CryptoFactory`].
+#[cfg(feature = "hardware-aes")]
+pub trait HardwareAes128: Default {
+    fn encrypt_block(&self, key: &AES128, block: &mut [u8; 16]);
+    fn decrypt_block(&self, key: &AES128, block: &mut [u8; 16]);
+}
+
+#[cfg(feature = "hardware-aes")]
+pub struct HwEnc<H: HardwareAes128> {
+    engine: H,
+    key: AES128,
+}
+
+#[cfg(feature = "hardware-aes")]
+impl<H: HardwareAes128> Encrypter for HwEnc<H> {
+    fn encrypt_block(&self, block: &mut [u8; 16]) {
+        self.engine.encrypt_block(&self.key, block);
+    }
+}
+
+#[cfg(feature = "hardware-aes")]
+pub struct HwDec<H: HardwareAes128> {
+    engine: H,
+    key: AES128,
+}
+
+#[cfg(feature = "hardware-aes")]
+impl<H: HardwareAes128> Decrypter for HwDec<H> {
+    fn decrypt_block(&self, block: &mut [u8; 16]) {
+        self.engine.decrypt_block(&self.key, block);
+    }
+}
+
+/// A [`CryptoFactory`] that delegates AES-128 block encryption and decryption
+/// to a board's [`HardwareAes128`] engine. The MIC (AES-CMAC) is still
+/// computed by `lorawan_encoding`'s pure-Rust `DefaultFactory`.
+#[cfg(feature = "hardware-aes")]
+#[derive(Default)]
+pub struct HardwareCrypto<H: HardwareAes128>(PhantomData<H>);
+
+#[cfg(feature = "hardware-aes")]
+impl<H: HardwareAes128> CryptoFactory for HardwareCrypto<H> {
+    type E = HwEnc<H>;
+    type D = HwDec<H>;
+    type M = <lorawan_encoding::default_crypto::DefaultFactory as CryptoFactory>::M;
+
+    fn new_enc(&self, key: &AES128) -> Self::E {
+        HwEnc {
+            engine: H::default(),
+            key: *key,
+        }
+    }
+
+    fn new_dec(&self, key: &AES128) -> Self::D {
+        HwDec {
+            engine: H::default(),
+            key: *key,
+        }
+    }
+
+    fn new_mac(&self, key: &AES128) -> Self::M {
+        lorawan_encoding::default_crypto::DefaultFactory.new_mac(key)
+    }
+}
@@ -14,6 +14,8 @@ pub use types::*;
 pub mod region;
 pub use region::Region;
 
+pub mod crypto;
+
 mod state_machines;
 use core::marker::PhantomData;
 use lorawan_encoding::{
@@ -25,13 +27,16 @@ pub use state_machines::{no_session, no_session::SessionData, session, JoinAccep
 
 type TimestampMs = u32;
 
-pub struct Device<'a, R, C>
+pub struct Device<R, C, B = DefaultBoardEirp>
 where
     R: radio::PhyRxTx + Timings,
     C: CryptoFactory + Default,
+    B: BoardEirp + Default,
 {
-    state: State<'a, R>,
+    shared: Shared<R>,
+    state: Option<State>,
     crypto: PhantomData<C>,
+    board: B,
 }
 
 type FcntDown = u32;
@@ -98,31 +103,23 @@ pub struct SendData<'a> {
     confirmed: bool,
 }
 
-pub enum State<'a, R>
-where
-    R: radio::PhyRxTx + Timings,
-{
-    NoSession(no_session::NoSession<'a, R>),
-    Session(session::Session<'a, R>),
+// `State` only tags which state machine is active; the `Shared` data (radio,
+// region config, TX/RX buffer) that used to live inside each variant now
+// lives once on `Device` so it isn't duplicated across state transitions.
+pub enum State {
+    NoSession(no_session::NoSession),
+    Session(session::Session),
 }
 
 use core::default::Default;
-impl<'a, R> State<'a, R>
-where
-    R: radio::PhyRxTx + Timings,
-{
-    fn new(shared: &'a mut Shared<'a, R>) -> Self {
-        State::NoSession(no_session::NoSession::new(shared))
+impl State {
+    fn new() -> Self {
+        State::NoSession(no_session::NoSession::new())
     }
 
-    fn new_abp(
-        shared: &'a mut Shared<'a, R>,
-        newskey: AES128,
-        appskey: AES128,
-        devaddr: DevAddr<[u8; 4]>,
-    ) -> Self {
+    fn new_abp(newskey: AES128, appskey: AES128, devaddr: DevAddr<[u8; 4]>) -> Self {
         let session_data = SessionData::new(newskey, appskey, devaddr);
-        State::Session(session::Session::new(shared, session_data))
+        State::Session(session::Session::new(session_data))
     }
 }
 
@@ -131,6 +128,56 @@ pub trait Timings {
     fn get_rx_window_duration_ms(&self) -> u32;
 }
 
+/// Board-specific radiated power characteristics.
+///
+/// The region tables only know the regulatory ceiling for a channel; the
+/// conducted power the radio should actually be told to emit also depends on
+/// the antenna gain and cable/matching loss of the board it's soldered to.
+/// Implement this trait to describe those so `Device` can derive a
+/// conducted TX power that keeps the *radiated* power within both the
+/// board's own limit and the region's limit.
+pub trait BoardEirp {
+    /// The board's own maximum EIRP, in dBm (antenna gain included).
+    fn max_eirp(&self) -> i8;
+    /// Passive antenna gain, in dBi.
+    fn antenna_gain(&self) -> i8;
+    /// Cumulative cable and matching-network loss between radio and antenna, in dB.
+    fn cable_loss(&self) -> i8;
+}
+
+/// A no-op `BoardEirp` that imposes no board-specific limit, so existing
+/// callers that don't care about antenna gain keep using the region ceiling
+/// verbatim.
+#[derive(Default)]
+pub struct DefaultBoardEirp;
+
+impl BoardEirp for DefaultBoardEirp {
+    fn max_eirp(&self) -> i8 {
+        i8::MAX
+    }
+
+    fn antenna_gain(&self) -> i8 {
+        0
+    }
+
+    fn cable_loss(&self) -> i8 {
+        0
+    }
+}
+
+/// Conducted TX power to program into the radio so that the *radiated*
+/// power (conducted + antenna gain - cable loss) stays within both the
+/// region's regulatory EIRP ceiling and the board's own EIRP limit.
+///
+/// This does not clamp to the radio's own supported TX-power range; callers
+/// that care about that should clamp the result themselves against
+/// whatever range their radio driver reports.
+pub(crate) fn conducted_tx_power(region_max_eirp: i8, board: &impl BoardEirp) -> i8 {
+    let eirp = region_max_eirp.min(board.max_eirp());
+    eirp.saturating_sub(board.antenna_gain())
+        .saturating_add(board.cable_loss())
+}
+
 pub enum JoinMode {
     OTAA {
         deveui: [u8; 8],
@@ -144,83 +191,94 @@ pub enum JoinMode {
     },
 }
 
-pub fn new_state<'a, R>(
+pub fn new_state<R>(
     region: region::Configuration,
     radio: R,
     get_random: fn() -> u32,
-    tx_buffer: &'a mut [u8],
-) -> Shared<'a, R>
+    tx_buffer: &mut [u8],
+) -> Shared<R>
 where
-    R: radio::PhyRxTx + Timings + 'a,
+    R: radio::PhyRxTx + Timings,
 {
-    Shared::new(radio, None, region, Mac::default(), get_random, tx_buffer)
+    assert!(
+        tx_buffer.len() <= 256,
+        "tx_buffer of {} bytes exceeds the 256-byte TX/RX buffer capacity",
+        tx_buffer.len()
+    );
+    let buffer = Vec::from_slice(tx_buffer).unwrap();
+    Shared::new(radio, None, region, Mac::default(), get_random, buffer)
 }
 
 #[allow(dead_code)]
-impl<'a, R, C> Device<'a, R, C>
+impl<R, C, B> Device<R, C, B>
 where
-    R: radio::PhyRxTx + Timings + 'a,
+    R: radio::PhyRxTx + Timings,
     C: CryptoFactory + Default,
+    B: BoardEirp + Default,
 {
-    pub fn new(join_mode: JoinMode, shared: &'a mut Shared<'a, R>) -> Device<'_, R, C> {
+    pub fn new(join_mode: JoinMode, shared: Shared<R>) -> Device<R, C, B> {
+        Self::new_with_board(join_mode, shared, B::default())
+    }
+
+    /// Like [`Device::new`], but with an explicit [`BoardEirp`] describing
+    /// this board's antenna gain and cable loss instead of the no-op default.
+    pub fn new_with_board(join_mode: JoinMode, mut shared: Shared<R>, board: B) -> Device<R, C, B> {
+        let state = match join_mode {
+            JoinMode::OTAA {
+                deveui,
+                appeui,
+                appkey,
+            } => {
+                shared
+                    .get_mut_credentials()
+                    .replace(Credentials::new(appeui, deveui, appkey));
+                State::new()
+            }
+            JoinMode::ABP {
+                newskey,
+                appskey,
+                devaddr,
+            } => State::new_abp(newskey, appskey, devaddr),
+        };
         Device {
+            shared,
+            state: Some(state),
             crypto: PhantomData::default(),
-            state: match join_mode {
-                JoinMode::OTAA {
-                    deveui,
-                    appeui,
-                    appkey,
-                } => {
-                        shared
-                        .get_mut_credentials()
-                        .replace(Credentials::new(appeui, deveui, appkey));
-                    State::new(shared)
-                }
-                JoinMode::ABP {
-                    newskey,
-                    appskey,
-                    devaddr,
-                } => State::new_abp(shared, newskey, appskey, devaddr),
-            },
+            board,
         }
     }
 
     pub fn get_radio(&mut self) -> &mut R {
-        let shared = self.get_shared();
-        shared.get_mut_radio()
+        self.shared.get_mut_radio()
     }
 
     pub fn get_credentials(&mut self) -> &mut Option<Credentials> {
-        let shared = self.get_shared();
-        shared.get_mut_credentials()
-    }
-
-    fn get_shared(&mut self) -> &mut Shared<'a, R> {
-        match &mut self.state {
-            State::NoSession(state) => state.get_mut_shared(),
-            State::Session(state) => state.get_mut_shared(),
-        }
+        self.shared.get_mut_credentials()
     }
 
     pub fn get_datarate(&mut self) -> region::DR {
-        self.get_shared().get_datarate()
+        self.shared.get_datarate()
     }
 
     pub fn set_datarate(&mut self, datarate: region::DR) {
-        self.get_shared().set_datarate(datarate);
+        self.shared.set_datarate(datarate);
+    }
+
+    pub fn get_board(&self) -> &B {
+        &self.board
     }
 
     pub fn ready_to_send_data(&self) -> bool {
-        matches!(&self.state, State::Session(session::Session::Idle(_)))
+        matches!(&self.state, Some(State::Session(session::Session::Idle(_))))
     }
 
     #[cfg(not(feature = "async"))]
     pub fn send(
-        self,
+        &mut self,
         data: &[u8],
         fport: u8,
         confirmed: bool,
-    ) -> (Self, Result<Response, Error<R>>) {
+    ) -> Result<Response, Error<R>> {
         self.handle_event(Event::SendDataRequest(SendData {
             data,
             fport,
@@ -229,14 +287,9 @@ where
     }
 
     #[cfg(feature = "async")]
-    pub async fn send<'m>(
-        self,
-        data: &'m [u8],
-        fport: u8,
-        confirmed: bool,
-    ) -> (Device<'a, R, C>, Result<Response, Error<R>>)
+    pub async fn send<'m>(&mut self, data: &'m [u8], fport: u8, confirmed: bool) -> Result<Response, Error<R>>
     where
-        Self: 'm,
+        R: 'm,
     {
         self.handle_event(Event::SendDataRequest(SendData {
             data,
@@ -247,7 +300,7 @@ where
     }
 
     pub fn get_fcnt_up(&self) -> Option<u32> {
-        if let State::Session(session) = &self.state {
+        if let Some(State::Session(session)) = &self.state {
             Some(session.get_session_data().fcnt_up())
         } else {
             None
@@ -255,7 +308,7 @@ where
     }
 
     pub fn get_session_keys(&self) -> Option<SessionKeys> {
-        if let State::Session(session) = &self.state {
+        if let Some(State::Session(session)) = &self.state {
             Some(SessionKeys::copy_from_session_data(
                 session.get_session_data(),
             ))
@@ -265,32 +318,62 @@ where
     }
 
     pub fn take_data_downlink(&mut self) -> Option<DecryptedDataPayload<Vec<u8, 256>>> {
-        self.get_shared().take_data_downlink()
+        self.shared.take_data_downlink()
     }
 
     pub fn take_join_accept(&mut self) -> Option<JoinAccept> {
-        self.get_shared().take_join_accept()
+        self.shared.take_join_accept()
     }
 
     #[cfg(not(feature = "async"))]
-    pub fn handle_event(self, event: Event<R>) -> (Self, Result<Response, Error<R>>) {
-        match self.state {
-            State::NoSession(state) => state.handle_event(event),
-            State::Session(state) => state.handle_event(event),
-        }
+    pub fn handle_event(&mut self, event: Event<R>) -> Result<Response, Error<R>> {
+        let (state, result) = match self.state.take().unwrap() {
+            State::NoSession(state) => state.handle_event(event, &mut self.shared, &self.board),
+            State::Session(state) => state.handle_event(event, &mut self.shared, &self.board),
+        };
+        self.state = Some(state);
+        result
     }
 
     #[cfg(feature = "async")]
-    pub async fn handle_event<'m>(
-        self,
-        event: Event<'m, R>,
-    ) -> (Device<'a, R, C>, Result<Response, Error<R>>)
+    pub async fn handle_event<'m>(&mut self, event: Event<'m, R>) -> Result<Response, Error<R>>
     where
-        Self: 'm,
+        R: 'm,
     {
-        match self.state {
-            State::NoSession(state) => state.handle_event(event).await,
-            State::Session(state) => state.handle_event(event).await,
-        }
+        let (state, result) = match self.state.take().unwrap() {
+            State::NoSession(state) => state.handle_event(event, &mut self.shared, &self.board).await,
+            State::Session(state) => state.handle_event(event, &mut self.shared, &self.board).await,
+        };
+        self.state = Some(state);
+        result
+    }
+}
+
+#[cfg(feature = "software")]
+impl<R, B> Device<R, crypto::SoftwareCrypto, B>
+where
+    R: radio::PhyRxTx + Timings,
+    B: BoardEirp + Default,
+{
+    /// Like [`Device::new`], but picks the `software` feature's
+    /// [`SoftwareCrypto`](crypto::SoftwareCrypto) backend so `C` doesn't need
+    /// to be named at the call site.
+    pub fn new_default(join_mode: JoinMode, shared: Shared<R>) -> Self {
+        Device::new(join_mode, shared)
+    }
+}
+
+#[cfg(feature = "hardware-aes")]
+impl<R, H, B> Device<R, crypto::HardwareCrypto<H>, B>
+where
+    R: radio::PhyRxTx + Timings,
+    H: crypto::HardwareAes128,
+    B: BoardEirp + Default,
+{
+    /// Like [`Device::new`], but picks the `hardware-aes` feature's
+    /// [`HardwareCrypto`](crypto::HardwareCrypto) backend so `C` doesn't need
+    /// to be named at the call site.
+    pub fn new_default(join_mode: JoinMode, shared: Shared<R>) -> Self {
+        Device::new(join_mode, shared)
     }
 }
@@ -6,7 +6,7 @@ pub mod session;
 
 pub struct Shared<R: radio::PhyRxTx + Timings> {
     radio: R,
-    credentials: Credentials,
+    credentials: Option<Credentials>,
     region: region::Configuration,
     mac: Mac,
     // TODO: do something nicer for randomness
@@ -30,7 +30,7 @@ impl<R: radio::PhyRxTx + Timings> Shared<R> {
     pub fn get_mut_radio(&mut self) -> &mut R {
         &mut self.radio
     }
-    pub fn get_mut_credentials(&mut self) -> &mut Credentials {
+    pub fn get_mut_credentials(&mut self) -> &mut Option<Credentials> {
         &mut self.credentials
     }
     pub fn get_datarate(&mut self) -> usize {
@@ -60,7 +60,7 @@ impl<R: radio::PhyRxTx + Timings> Shared<R> {
 impl<R: radio::PhyRxTx + Timings> Shared<R> {
     pub fn new(
         radio: R,
-        credentials: Credentials,
+        credentials: Option<Credentials>,
         region: region::Configuration,
         mac: Mac,
         get_random: fn() -> u32,
@@ -78,7 +78,3 @@ impl<R: radio::PhyRxTx + Timings> Shared<R> {
         }
     }
 }
-
-trait CommonState<R: radio::PhyRxTx + Timings> {
-    fn get_mut_shared(&mut self) -> &mut Shared<R>;
-}
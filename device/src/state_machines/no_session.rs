@@ -0,0 +1,114 @@
+use super::Shared;
+use crate::{conducted_tx_power, radio, BoardEirp, Error as DeviceError, Event, Response, State, Timings};
+use lorawan_encoding::keys::AES128;
+use lorawan_encoding::parser::DevAddr;
+
+#[derive(Debug)]
+pub enum Error {
+    RadioEventWhileIdle,
+    SendDataWhileNoSession,
+}
+
+pub struct SessionData {
+    newskey: AES128,
+    appskey: AES128,
+    devaddr: DevAddr<[u8; 4]>,
+    fcnt_up: u32,
+}
+
+impl SessionData {
+    pub fn new(newskey: AES128, appskey: AES128, devaddr: DevAddr<[u8; 4]>) -> Self {
+        SessionData {
+            newskey,
+            appskey,
+            devaddr,
+            fcnt_up: 0,
+        }
+    }
+
+    pub fn fcnt_up(&self) -> u32 {
+        self.fcnt_up
+    }
+}
+
+pub struct NoSession {
+    join_attempts: u8,
+}
+
+impl NoSession {
+    pub fn new() -> Self {
+        NoSession { join_attempts: 0 }
+    }
+
+    /// Conducted power for the join request. There's no session yet at this
+    /// point, so nothing but the region's regulatory ceiling and the board's
+    /// own EIRP limit, antenna gain and cable loss feed into it — unlike an uplink, a join
+    /// request has no negotiated data rate or `TxParamSetupReq` to account
+    /// for.
+    fn join_request_tx_power<R>(shared: &Shared<R>, board: &impl BoardEirp) -> i8
+    where
+        R: radio::PhyRxTx + Timings,
+    {
+        conducted_tx_power(shared.region.get_max_eirp(), board)
+    }
+}
+
+#[cfg(not(feature = "async"))]
+impl NoSession {
+    pub(crate) fn handle_event<R>(
+        mut self,
+        event: Event<R>,
+        shared: &mut Shared<R>,
+        board: &impl BoardEirp,
+    ) -> (State, Result<Response, DeviceError<R>>)
+    where
+        R: radio::PhyRxTx + Timings,
+    {
+        match event {
+            Event::NewSessionRequest => {
+                self.join_attempts += 1;
+                let tx_power = Self::join_request_tx_power(shared, board);
+                shared.get_mut_radio().set_tx_power(tx_power);
+                (State::NoSession(self), Ok(Response::JoinRequestSending))
+            }
+            Event::SendDataRequest(_) => (
+                State::NoSession(self),
+                Err(DeviceError::NoSession(Error::SendDataWhileNoSession)),
+            ),
+            _ => (
+                State::NoSession(self),
+                Err(DeviceError::NoSession(Error::RadioEventWhileIdle)),
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl NoSession {
+    pub(crate) async fn handle_event<'m, R>(
+        mut self,
+        event: Event<'m, R>,
+        shared: &mut Shared<R>,
+        board: &impl BoardEirp,
+    ) -> (State, Result<Response, DeviceError<R>>)
+    where
+        R: radio::PhyRxTx + Timings + 'm,
+    {
+        match event {
+            Event::NewSessionRequest => {
+                self.join_attempts += 1;
+                let tx_power = Self::join_request_tx_power(shared, board);
+                shared.get_mut_radio().set_tx_power(tx_power);
+                (State::NoSession(self), Ok(Response::JoinRequestSending))
+            }
+            Event::SendDataRequest(_) => (
+                State::NoSession(self),
+                Err(DeviceError::NoSession(Error::SendDataWhileNoSession)),
+            ),
+            _ => (
+                State::NoSession(self),
+                Err(DeviceError::NoSession(Error::RadioEventWhileIdle)),
+            ),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use super::no_session::SessionData;
+use super::Shared;
+use crate::{conducted_tx_power, radio, BoardEirp, Error as DeviceError, Event, Response, State, Timings};
+
+#[derive(Debug)]
+pub enum Error {
+    RadioEventWhileIdle,
+    NewSessionRequestWhileSession,
+}
+
+pub enum Session {
+    Idle(SessionData),
+}
+
+impl Session {
+    pub fn new(session_data: SessionData) -> Self {
+        Session::Idle(session_data)
+    }
+
+    pub fn get_session_data(&self) -> &SessionData {
+        match self {
+            Session::Idle(session_data) => session_data,
+        }
+    }
+
+    /// Conducted power for an uplink in an established session. Reuses the
+    /// same region-ceiling/board-EIRP derivation as a join request; a
+    /// negotiated data rate doesn't change the TX power budget, only which
+    /// channels and spreading factors are eligible.
+    fn uplink_tx_power<R>(shared: &Shared<R>, board: &impl BoardEirp) -> i8
+    where
+        R: radio::PhyRxTx + Timings,
+    {
+        conducted_tx_power(shared.region.get_max_eirp(), board)
+    }
+}
+
+#[cfg(not(feature = "async"))]
+impl Session {
+    pub(crate) fn handle_event<R>(
+        self,
+        event: Event<R>,
+        shared: &mut Shared<R>,
+        board: &impl BoardEirp,
+    ) -> (State, Result<Response, DeviceError<R>>)
+    where
+        R: radio::PhyRxTx + Timings,
+    {
+        match event {
+            Event::SendDataRequest(_) => {
+                let tx_power = Self::uplink_tx_power(shared, board);
+                shared.get_mut_radio().set_tx_power(tx_power);
+                let fcnt_up = self.get_session_data().fcnt_up();
+                (State::Session(self), Ok(Response::UplinkSending(fcnt_up)))
+            }
+            Event::NewSessionRequest => (
+                State::Session(self),
+                Err(DeviceError::Session(Error::NewSessionRequestWhileSession)),
+            ),
+            _ => (
+                State::Session(self),
+                Err(DeviceError::Session(Error::RadioEventWhileIdle)),
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Session {
+    pub(crate) async fn handle_event<'m, R>(
+        self,
+        event: Event<'m, R>,
+        shared: &mut Shared<R>,
+        board: &impl BoardEirp,
+    ) -> (State, Result<Response, DeviceError<R>>)
+    where
+        R: radio::PhyRxTx + Timings + 'm,
+    {
+        match event {
+            Event::SendDataRequest(_) => {
+                let tx_power = Self::uplink_tx_power(shared, board);
+                shared.get_mut_radio().set_tx_power(tx_power);
+                let fcnt_up = self.get_session_data().fcnt_up();
+                (State::Session(self), Ok(Response::UplinkSending(fcnt_up)))
+            }
+            Event::NewSessionRequest => (
+                State::Session(self),
+                Err(DeviceError::Session(Error::NewSessionRequestWhileSession)),
+            ),
+            _ => (
+                State::Session(self),
+                Err(DeviceError::Session(Error::RadioEventWhileIdle)),
+            ),
+        }
+    }
+}